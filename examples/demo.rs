@@ -48,7 +48,7 @@ impl<'a, T: OutputSample<T>> Sequencer<'a, T> {
         loop {
             let written = self.producer.write_slices(
                 |data, _offset| {
-                    data.fill_with(|| self.sg.next_sample());
+                    self.sg.generate(data);
                     data.len()
                 },
                 Some(self.samples),
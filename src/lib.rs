@@ -8,7 +8,9 @@ constrained environments. It is designed with a focus on speed to be usable
 on 8-bit CPUs (such as AVR[^1]). Emulating the chip is not the goal. As such,
 there are significant differences from the AY-3-8910 in terms of functionality.
 
-* **Hardware Envelope:** Not implemented. Channel volume ranges from 0 to 15.
+* **Hardware Envelope:** Implemented as in the AY-3-8910, with a 16-bit period
+  register and the `Continue`/`Attack`/`Alternate`/`Hold` shape bits. Only one
+  envelope generator is shared across all channels, matching the hardware.
 * **Noise Generator:** The number of bits in the shift register differs. Specifically,
   it is 16 bits instead of 17 bits.
 * **Tone Period:** 0 cannot be set. The minimum value is constrained by the clock rate
@@ -31,6 +33,9 @@ In a PC environment, most audio frameworks automatically perform sample rate
 conversion before outputting to the device. Therefore, it is often possible to play
 back a sample rate of 250KHz without any issues.
 
+For cases where the thinning-out artifacts described above are not acceptable, the
+`antialias` feature trades the default speed for quality, see below.
+
 ## Features
 
 This crate has the following `features` flags:
@@ -38,6 +43,14 @@ This crate has the following `features` flags:
 `float`: Enables generating samples in `f32` format. Enabled by default. If this flag
 is not set, floating-point operations are not performed.
 
+`antialias`: Runs the tone and noise generators at an oversampled rate and decimates
+through a windowed-sinc FIR low-pass filter before producing each `f32` output sample.
+This removes most of the aliasing that the default thinning-out approach produces at
+low sample rates, at the cost of the extra oversampling and convolution work. Requires
+`float`. Not enabled by default. Since only the `f32` output path decimates the
+oversampled signal back down, enabling this feature removes the `i16` `OutputSample`
+impl entirely, rather than let `i16` output silently run at the wrong pitch.
+
 ## Usage
 
 To use this crate, create a `SoundGenerator` instance, configure the tone period and
@@ -71,8 +84,23 @@ use bitflags::bitflags;
 use core::{array, cmp};
 use paste::paste;
 
+#[cfg(feature = "antialias")]
+use libm::{cosf, sinf};
+
 const CHANNELS: usize = 3;
 
+/// Number of AY-3-8910 registers understood by `write_register` (R0-R13;
+/// the I/O port registers R14/R15 have no equivalent here).
+const AY_REGISTER_COUNT: usize = 14;
+
+/// The oversampling factor used by the `antialias` feature.
+#[cfg(feature = "antialias")]
+const OVERSAMPLE: u32 = 8;
+
+/// The number of taps in the `antialias` decimation FIR filter.
+#[cfg(feature = "antialias")]
+const FIR_TAPS: usize = 32;
+
 bitflags! {
 /// Indicates the output of the channel.
     /// Used in the channel output setting (set_mode).
@@ -89,12 +117,41 @@ bitflags! {
     }
 }
 
+/// Selects a channel's tone waveform shape.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Waveform {
+    /// A 50% duty square wave. The default.
+    #[default]
+    Square,
+    /// A square wave with a configurable duty cycle, see `set_duty`.
+    Pulse,
+    /// A triangle wave, ramping the amplitude up and down.
+    Triangle,
+    /// A sawtooth wave, ramping the amplitude up before resetting.
+    Sawtooth,
+}
+
+/// Number of amplitude steps a full `Waveform::Sawtooth` cycle ramps through.
+const SAWTOOTH_STEPS: u64 = 16;
+/// Number of amplitude steps a full `Waveform::Triangle` cycle ramps through
+/// (15 up, then 15 down).
+const TRIANGLE_STEPS: u64 = 30;
+
 struct ToneGenerator {
     clock_rate: u32,
     sample_rate_x8: u32,
     error: i64,
     period_min: u16,
-    source: u64,
+    period: u16,
+    source_high: u64,
+    source_low: u64,
+    high_phase: bool,
+    waveform: Waveform,
+    duty: u8,
+    ramp_error: i64,
+    ramp_source: u64,
+    ramp: u8,
+    ramp_up: bool,
     output: Output,
 }
 
@@ -102,37 +159,132 @@ impl ToneGenerator {
     fn new(clock_rate: u32, sample_rate: u32) -> Self {
         let sample_rate_x8 = sample_rate * 8;
         let period = (clock_rate / (sample_rate_x8 * 2) + 1) as u16;
-        let source = period as u64 * sample_rate_x8 as u64;
-        Self {
+        let mut generator = Self {
             clock_rate,
             sample_rate_x8,
             error: clock_rate as i64,
             period_min: period,
-            source,
+            period,
+            source_high: 0,
+            source_low: 0,
+            high_phase: true,
+            waveform: Waveform::default(),
+            duty: 128,
+            ramp_error: clock_rate as i64,
+            ramp_source: 0,
+            ramp: 0,
+            ramp_up: true,
             output: Output::NONE,
-        }
+        };
+        generator.update_thresholds();
+        generator
     }
 
     fn set_period(&mut self, period: u16) {
         assert!(period < 4096);
-        let period = cmp::max(period, self.period_min);
-        self.source = self.sample_rate_x8 as u64 * period as u64;
+        self.period = cmp::max(period, self.period_min);
+        self.update_thresholds();
     }
 
-    fn update(&mut self) -> Output {
+    fn set_waveform(&mut self, waveform: Waveform) {
+        self.waveform = waveform;
+        self.update_thresholds();
+    }
+
+    fn set_duty(&mut self, duty: u8) {
+        self.duty = duty;
+        self.update_thresholds();
+    }
+
+    fn waveform(&self) -> Waveform {
+        self.waveform
+    }
+
+    /// Recomputes the high/low dwell times in clock ticks from `period` and,
+    /// for `Waveform::Pulse`, `duty`. Every other waveform uses an even split.
+    ///
+    /// Also recomputes the ramp step duration for `Waveform::Triangle`/
+    /// `Waveform::Sawtooth`, so the whole 0-15 ramp completes once per full
+    /// tone cycle (two `period` dwells), matching the pitch of `Square` for
+    /// the same `period`, instead of once per half-period toggle.
+    fn update_thresholds(&mut self) {
+        let (high, low) = if self.waveform == Waveform::Pulse {
+            let cycle = 2 * self.period;
+            let high = cmp::max((cycle as u32 * self.duty as u32 / 256) as u16, 1);
+            (high, cmp::max(cycle.saturating_sub(high), 1))
+        } else {
+            (self.period, self.period)
+        };
+        self.source_high = self.sample_rate_x8 as u64 * high as u64;
+        self.source_low = self.sample_rate_x8 as u64 * low as u64;
+
+        let ramp_steps = match self.waveform {
+            Waveform::Sawtooth => SAWTOOTH_STEPS,
+            Waveform::Triangle => TRIANGLE_STEPS,
+            Waveform::Square | Waveform::Pulse => 1,
+        };
+        let full_cycle = 2 * self.period as u64;
+        let ramp_period = cmp::max((full_cycle + ramp_steps / 2) / ramp_steps, 1);
+        self.ramp_source = self.sample_rate_x8 as u64 * ramp_period;
+    }
+
+    /// Advances the generator by one sample tick.
+    ///
+    /// # Returns
+    /// A tuple of the gating `Output` (used to mute the channel, as with the
+    /// original square-only behavior) and the current waveform amplitude
+    /// (0-15), which only varies for `Waveform::Triangle`/`Waveform::Sawtooth`.
+    fn update(&mut self) -> (Output, u8) {
         self.error -= self.clock_rate as i64;
         if self.error < 0 {
-            self.error += self.source as i64;
-            self.output.toggle(Output::TONE);
+            let source = if self.high_phase {
+                self.source_high
+            } else {
+                self.source_low
+            };
+            self.error += source as i64;
+            self.high_phase = !self.high_phase;
+            if matches!(self.waveform, Waveform::Square | Waveform::Pulse) {
+                self.output.toggle(Output::TONE);
+            }
+        }
+        if matches!(self.waveform, Waveform::Triangle | Waveform::Sawtooth) {
+            self.ramp_error -= self.clock_rate as i64;
+            if self.ramp_error < 0 {
+                self.ramp_error += self.ramp_source as i64;
+                match self.waveform {
+                    Waveform::Triangle => {
+                        if self.ramp_up {
+                            self.ramp += 1;
+                            if self.ramp == 15 {
+                                self.ramp_up = false;
+                            }
+                        } else {
+                            self.ramp -= 1;
+                            if self.ramp == 0 {
+                                self.ramp_up = true;
+                            }
+                        }
+                    }
+                    Waveform::Sawtooth => self.ramp = (self.ramp + 1) & 0x0f,
+                    Waveform::Square | Waveform::Pulse => unreachable!(),
+                }
+            }
         }
-        self.output
+        (self.output, self.ramp)
     }
 }
 
+/// Center pan position for `Channel::pan`, giving near-equal left/right gain
+/// (127 and 128 out of 255).
+const PAN_CENTER: u8 = 128;
+
 struct Channel {
     generator: ToneGenerator,
     volume: u8,
     mode: Output,
+    use_envelope: bool,
+    pan: u8,
 }
 
 impl Channel {
@@ -141,6 +293,8 @@ impl Channel {
             generator: ToneGenerator::new(clock_rate, sample_rate),
             volume: 0,
             mode: Output::NONE,
+            use_envelope: false,
+            pan: PAN_CENTER,
         }
     }
 
@@ -148,6 +302,16 @@ impl Channel {
         self.generator.set_period(period)
     }
 
+    fn set_pan(&mut self, pan: u8) {
+        self.pan = pan
+    }
+
+    /// Returns the (left, right) linear gain weights for this channel's pan,
+    /// each in 0..=255.
+    fn pan_gains(&self) -> (u8, u8) {
+        (255 - self.pan, self.pan)
+    }
+
     fn set_mode(&mut self, mode: Output) {
         self.mode = mode
     }
@@ -157,15 +321,126 @@ impl Channel {
         self.volume = volume
     }
 
-    fn update(&mut self, noise: Output) -> u8 {
-        if (self.generator.update() | noise).contains(self.mode) {
+    fn set_use_envelope(&mut self, use_envelope: bool) {
+        self.use_envelope = use_envelope
+    }
+
+    fn set_waveform(&mut self, waveform: Waveform) {
+        self.generator.set_waveform(waveform)
+    }
+
+    fn set_duty(&mut self, duty: u8) {
+        self.generator.set_duty(duty)
+    }
+
+    fn update(&mut self, noise: Output, envelope: u8) -> u8 {
+        let (tone, ramp) = self.generator.update();
+        if (tone | noise).contains(self.mode) {
             0
         } else {
-            self.volume
+            let level = if self.use_envelope {
+                envelope
+            } else {
+                self.volume
+            };
+            match self.generator.waveform() {
+                Waveform::Triangle | Waveform::Sawtooth => {
+                    (level as u16 * ramp as u16 / 15) as u8
+                }
+                Waveform::Square | Waveform::Pulse => level,
+            }
         }
     }
 }
 
+bitflags! {
+    /// Selects the hardware envelope ramp shape.
+    ///
+    /// These bits mirror the AY-3-8910 envelope shape register (R13).
+    #[derive(Clone, Copy)]
+    pub struct EnvelopeShape: u8 {
+        /// Hold after the first ramp: at the level it ended on, or at the
+        /// opposite level when `ALTERNATE` is also set.
+        const HOLD = 0b0001;
+        /// Alternate the ramp direction every cycle.
+        const ALTERNATE = 0b0010;
+        /// Count up (0 to 15) instead of down (15 to 0).
+        const ATTACK = 0b0100;
+        /// Keep cycling instead of dropping to 0 and holding after one ramp.
+        const CONTINUE = 0b1000;
+    }
+}
+
+struct EnvelopeGenerator {
+    clock_rate: u32,
+    sample_rate_x256: u64,
+    error: i64,
+    source: u64,
+    phase: i8,
+    attack: u8,
+    shape: EnvelopeShape,
+    holding: bool,
+}
+
+impl EnvelopeGenerator {
+    fn new(clock_rate: u32, sample_rate: u32) -> Self {
+        let sample_rate_x256 = sample_rate as u64 * 256;
+        Self {
+            clock_rate,
+            sample_rate_x256,
+            error: clock_rate as i64,
+            source: sample_rate_x256,
+            phase: 31,
+            attack: 0,
+            shape: EnvelopeShape::empty(),
+            holding: false,
+        }
+    }
+
+    fn set_period(&mut self, period: u16) {
+        self.source = cmp::max(period, 1) as u64 * self.sample_rate_x256;
+    }
+
+    fn set_shape(&mut self, shape: EnvelopeShape) {
+        self.shape = shape;
+        self.phase = 31;
+        self.holding = false;
+        self.attack = if shape.contains(EnvelopeShape::ATTACK) {
+            0x1f
+        } else {
+            0x00
+        };
+    }
+
+    fn update(&mut self) -> u8 {
+        if !self.holding {
+            self.error -= self.clock_rate as i64;
+            if self.error < 0 {
+                self.error += self.source as i64;
+                self.phase -= 1;
+                if self.phase < 0 {
+                    if self.shape.contains(EnvelopeShape::CONTINUE) {
+                        if self.shape.contains(EnvelopeShape::ALTERNATE) {
+                            self.attack ^= 0x1f;
+                        }
+                        if self.shape.contains(EnvelopeShape::HOLD) {
+                            self.holding = true;
+                            self.phase = 0;
+                        } else {
+                            self.phase = 31;
+                        }
+                    } else {
+                        self.holding = true;
+                        self.phase = 0;
+                        self.attack = 0;
+                    }
+                }
+            }
+        }
+        (self.phase as u8 ^ self.attack) >> 1
+    }
+}
+
 struct NoiseGenerator {
     clock_rate: u32,
     sample_rate_x16: u32,
@@ -210,12 +485,72 @@ impl NoiseGenerator {
     }
 }
 
+/// Oversampled-mix ring buffer and windowed-sinc FIR used by the `antialias`
+/// feature to decimate back down to the output sample rate.
+#[cfg(feature = "antialias")]
+struct Decimator {
+    taps: [f32; FIR_TAPS],
+    ring: [f32; FIR_TAPS],
+    pos: usize,
+}
+
+#[cfg(feature = "antialias")]
+impl Decimator {
+    fn new() -> Self {
+        const PI: f32 = core::f32::consts::PI;
+        let fc = 0.45 / OVERSAMPLE as f32;
+        let m = (FIR_TAPS - 1) as f32;
+        let mut taps = [0.0f32; FIR_TAPS];
+        let mut gain = 0.0f32;
+        for (k, tap) in taps.iter_mut().enumerate() {
+            let x = 2.0 * fc * (k as f32 - m / 2.0);
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                sinf(PI * x) / (PI * x)
+            };
+            // Blackman window.
+            let w = 0.42 - 0.5 * cosf(2.0 * PI * k as f32 / m) + 0.08 * cosf(4.0 * PI * k as f32 / m);
+            *tap = sinc * w;
+            gain += *tap;
+        }
+        taps.iter_mut().for_each(|tap| *tap /= gain);
+        Self {
+            taps,
+            ring: [0.0; FIR_TAPS],
+            pos: 0,
+        }
+    }
+
+    /// Pushes one oversampled mix value into the ring buffer.
+    fn push(&mut self, value: f32) {
+        self.ring[self.pos] = value;
+        self.pos = (self.pos + 1) % FIR_TAPS;
+    }
+
+    /// Convolves the ring buffer with the FIR taps, producing one decimated
+    /// output sample.
+    fn convolve(&self) -> f32 {
+        self.taps
+            .iter()
+            .enumerate()
+            .map(|(i, h)| h * self.ring[(self.pos + i) % FIR_TAPS])
+            .sum()
+    }
+}
+
 /// Generates waveforms for PSG.
 pub struct SoundGenerator {
     clock_rate: u32,
     sample_rate: u32,
     channels: [Channel; CHANNELS],
     noise: NoiseGenerator,
+    envelope: EnvelopeGenerator,
+    #[cfg(feature = "antialias")]
+    decimator: Decimator,
+    #[cfg(feature = "antialias")]
+    decimator_r: Decimator,
+    registers: [u8; AY_REGISTER_COUNT],
 }
 
 impl SoundGenerator {
@@ -228,11 +563,23 @@ impl SoundGenerator {
     /// # Returns
     /// A new `SoundGenerator` instance.
     pub fn new(clock_rate: u32, sample_rate: u32) -> Self {
+        // With `antialias`, the tone/noise/envelope generators tick at an
+        // oversampled rate so `Decimator` has a dense enough signal to filter.
+        #[cfg(feature = "antialias")]
+        let internal_rate = sample_rate * OVERSAMPLE;
+        #[cfg(not(feature = "antialias"))]
+        let internal_rate = sample_rate;
         Self {
             clock_rate,
             sample_rate,
-            channels: array::from_fn(|_| Channel::new(clock_rate, sample_rate)),
-            noise: NoiseGenerator::new(clock_rate, sample_rate),
+            channels: array::from_fn(|_| Channel::new(clock_rate, internal_rate)),
+            noise: NoiseGenerator::new(clock_rate, internal_rate),
+            envelope: EnvelopeGenerator::new(clock_rate, internal_rate),
+            #[cfg(feature = "antialias")]
+            decimator: Decimator::new(),
+            #[cfg(feature = "antialias")]
+            decimator_r: Decimator::new(),
+            registers: [0; AY_REGISTER_COUNT],
         }
     }
 
@@ -279,6 +626,26 @@ impl SoundGenerator {
         self.channels[channel].set_mode(mode)
     }
 
+    /// Sets the waveform shape for the specified channel.
+    ///
+    /// # Arguments
+    /// - `channel`: The channel number (0-2).
+    /// - `waveform`: The waveform shape. Defaults to `Waveform::Square`.
+    pub fn set_waveform(&mut self, channel: usize, waveform: Waveform) {
+        self.channels[channel].set_waveform(waveform)
+    }
+
+    /// Sets the duty cycle for the specified channel's `Waveform::Pulse`.
+    /// Ignored for every other waveform.
+    ///
+    /// # Arguments
+    /// - `channel`: The channel number (0-2).
+    /// - `duty`: The high-portion fraction of the period, as `duty / 256`.
+    ///   128 is a 50% duty cycle, matching `Waveform::Square`.
+    pub fn set_duty(&mut self, channel: usize, duty: u8) {
+        self.channels[channel].set_duty(duty)
+    }
+
     /// Sets the noise period.
     ///
     /// # Arguments
@@ -287,6 +654,110 @@ impl SoundGenerator {
         self.noise.set_period(period)
     }
 
+    /// Sets the envelope period.
+    ///
+    /// # Arguments
+    /// - `period`: The 16-bit envelope period register value.
+    pub fn set_envelope_period(&mut self, period: u16) {
+        self.envelope.set_period(period)
+    }
+
+    /// Sets the envelope shape.
+    ///
+    /// # Arguments
+    /// - `shape`: The envelope shape (logical OR of `Continue`, `Attack`,
+    ///   `Alternate`, and `Hold`).
+    pub fn set_envelope_shape(&mut self, shape: EnvelopeShape) {
+        self.envelope.set_shape(shape)
+    }
+
+    /// Sets whether the specified channel's volume is driven by the shared
+    /// envelope generator instead of its fixed volume.
+    ///
+    /// # Arguments
+    /// - `channel`: The channel number (0-2).
+    /// - `use_envelope`: `true` to use the envelope level, `false` to use the
+    ///   fixed volume set via `set_volume`.
+    pub fn set_use_envelope(&mut self, channel: usize, use_envelope: bool) {
+        self.channels[channel].set_use_envelope(use_envelope)
+    }
+
+    /// Sets the stereo pan position for the specified channel.
+    ///
+    /// # Arguments
+    /// - `channel`: The channel number (0-2).
+    /// - `pan`: The pan position, from 0 (full left) to 255 (full right).
+    ///   128 is centered. Defaults to centered.
+    pub fn set_pan(&mut self, channel: usize, pan: u8) {
+        self.channels[channel].set_pan(pan)
+    }
+
+    /// Writes an AY-3-8910 register, translating it to the equivalent setter
+    /// calls. This lets `SoundGenerator` be driven directly from recorded PSG
+    /// register dumps.
+    ///
+    /// # Arguments
+    /// - `addr`: The register number (R0-R13). R0/R1, R2/R3, and R4/R5 are the
+    ///   fine/coarse tone period for channels 0-2; R6 is the noise period; R7
+    ///   is the mixer byte (bit layout matches the AY-3-8910: a clear bit
+    ///   enables that channel's tone/noise); R8-R10 are channel volumes, with
+    ///   bit 4 selecting the envelope; R11/R12 are the fine/coarse envelope
+    ///   period; R13 is the envelope shape. Registers outside R0-R13 (the
+    ///   AY-3-8910 I/O ports) are ignored.
+    /// - `value`: The value to write to the register.
+    pub fn write_register(&mut self, addr: u8, value: u8) {
+        let index = addr as usize;
+        if index >= AY_REGISTER_COUNT {
+            return;
+        }
+        self.registers[index] = value;
+        match index {
+            0 | 1 => self.update_tone_period(0),
+            2 | 3 => self.update_tone_period(1),
+            4 | 5 => self.update_tone_period(2),
+            6 => self.set_noise_period(value & 0x1f),
+            7 => self.update_mixer(),
+            8 => self.update_volume(0),
+            9 => self.update_volume(1),
+            10 => self.update_volume(2),
+            11 | 12 => self.update_envelope_period(),
+            13 => self.set_envelope_shape(EnvelopeShape::from_bits_truncate(value)),
+            _ => unreachable!(),
+        }
+    }
+
+    fn update_tone_period(&mut self, channel: usize) {
+        let fine = self.registers[channel * 2] as u16;
+        let coarse = (self.registers[channel * 2 + 1] & 0x0f) as u16;
+        self.set_period(channel, (coarse << 8) | fine);
+    }
+
+    fn update_mixer(&mut self) {
+        let mixer = self.registers[7];
+        for channel in 0..CHANNELS {
+            let mut mode = Output::NONE;
+            if mixer & (1 << channel) == 0 {
+                mode |= Output::TONE;
+            }
+            if mixer & (1 << (channel + 3)) == 0 {
+                mode |= Output::NOISE;
+            }
+            self.set_mode(channel, mode);
+        }
+    }
+
+    fn update_volume(&mut self, channel: usize) {
+        let value = self.registers[8 + channel];
+        self.set_volume(channel, value & 0x0f);
+        self.set_use_envelope(channel, value & 0x10 != 0);
+    }
+
+    fn update_envelope_period(&mut self) {
+        let fine = self.registers[11] as u16;
+        let coarse = self.registers[12] as u16;
+        self.set_envelope_period((coarse << 8) | fine);
+    }
+
     /// Generates and returns the next sample value.
     ///
     /// # Returns
@@ -298,6 +769,49 @@ impl SoundGenerator {
     pub fn next_sample<T: OutputSample<T>>(&mut self) -> T {
         T::next_sample(self)
     }
+
+    /// Fills `out` with successive sample values, one call per slice instead
+    /// of one call per sample.
+    ///
+    /// This amortizes the per-call dispatch overhead of `next_sample`, which
+    /// matters most on the low-end 8-bit targets this crate is designed for.
+    ///
+    /// # Arguments
+    /// - `out`: The slice to fill with generated samples.
+    ///
+    /// # Note
+    /// T must implement the `OutputSample` trait. `OutputSample` is implemented
+    /// for `f32` and `i16`.
+    pub fn generate<T: OutputSample<T>>(&mut self, out: &mut [T]) {
+        T::fill(self, out)
+    }
+
+    /// Generates and returns the next stereo sample pair, applying each
+    /// channel's pan (see `set_pan`).
+    ///
+    /// # Returns
+    /// A `(left, right)` sample pair of type T.
+    ///
+    /// # Note
+    /// T must implement the `OutputSample` trait. `OutputSample` is implemented
+    /// for `f32` and `i16`.
+    pub fn next_sample_stereo<T: OutputSample<T>>(&mut self) -> (T, T) {
+        T::next_sample_stereo(self)
+    }
+
+    /// Fills `out` with successive stereo sample pairs, one call per slice
+    /// instead of one call per sample, mirroring `generate`.
+    ///
+    /// # Arguments
+    /// - `out`: The slice of `(left, right)` pairs to fill.
+    ///
+    /// # Note
+    /// T must implement the `OutputSample` trait. `OutputSample` is implemented
+    /// for `f32` and `i16`.
+    pub fn generate_stereo<T: OutputSample<T>>(&mut self, out: &mut [(T, T)]) {
+        out.iter_mut()
+            .for_each(|sample| *sample = T::next_sample_stereo(self));
+    }
 }
 
 /// A trait for generating sample values.
@@ -311,6 +825,46 @@ pub trait OutputSample<T> {
     /// # Returns
     /// A sample value of type T.
     fn next_sample(sg: &mut SoundGenerator) -> T;
+
+    /// Fills `out` with successive sample values in a single pass.
+    ///
+    /// Unlike repeated `next_sample` calls, an implementation can hoist
+    /// per-tick work (such as the noise/envelope update) once per loop body
+    /// instead of paying dispatch overhead for every element.
+    ///
+    /// # Arguments
+    /// - `sg`: A reference to the `SoundGenerator`.
+    /// - `out`: The slice to fill with generated samples.
+    fn fill(sg: &mut SoundGenerator, out: &mut [T]);
+
+    /// Generates and returns the next stereo sample pair, applying each
+    /// channel's pan (see `SoundGenerator::set_pan`).
+    ///
+    /// # Arguments
+    /// - `sg`: A reference to the `SoundGenerator`.
+    ///
+    /// # Returns
+    /// A `(left, right)` sample pair of type T.
+    fn next_sample_stereo(sg: &mut SoundGenerator) -> (T, T);
+}
+
+/// Scales a mixed sample by a 0..=255 pan gain without losing the low bits
+/// of small `i16` values to premature truncation.
+trait PanScale: Sized {
+    fn scale_pan(self, gain: u8) -> Self;
+}
+
+impl PanScale for i16 {
+    fn scale_pan(self, gain: u8) -> i16 {
+        ((self as i32 * gain as i32) / 255) as i16
+    }
+}
+
+#[cfg(feature = "float")]
+impl PanScale for f32 {
+    fn scale_pan(self, gain: u8) -> f32 {
+        self * gain as f32 / 255.0
+    }
 }
 
 macro_rules! output_mixer_table_impl {
@@ -358,39 +912,247 @@ macro_rules! output_mixer_impl {
                 15446,
                 21845
             ]);
+        )*
+
+    }
+}
+
+// Unused when `antialias` pulls in both `float` and `antialias`: neither the
+// `i16` nor the `f32` invocation below fires, since both require
+// `not(feature = "antialias")`.
+#[allow(unused_macros)]
+macro_rules! output_mixer_default_impl {
+    ($($T:ty)*) => {
+        $(
             paste! {
                 impl OutputSample<$T> for $T {
                     fn next_sample(generator: &mut SoundGenerator) -> $T {
                         let noise = generator.noise.update();
+                        let envelope = generator.envelope.update();
                         generator
                             .channels
                             .iter_mut()
                             .fold(Default::default(), |sum, channel| {
-                                sum + unsafe { [<OUTPUT_VOLUME_TABLE_$T>].get_unchecked(channel.update(noise) as usize) }
+                                sum + unsafe { [<OUTPUT_VOLUME_TABLE_$T>].get_unchecked(channel.update(noise, envelope) as usize) }
                             })
                     }
+
+                    fn fill(generator: &mut SoundGenerator, out: &mut [$T]) {
+                        for sample in out.iter_mut() {
+                            let noise = generator.noise.update();
+                            let envelope = generator.envelope.update();
+                            *sample = generator
+                                .channels
+                                .iter_mut()
+                                .fold(Default::default(), |sum, channel| {
+                                    sum + unsafe { [<OUTPUT_VOLUME_TABLE_$T>].get_unchecked(channel.update(noise, envelope) as usize) }
+                                });
+                        }
+                    }
+
+                    fn next_sample_stereo(generator: &mut SoundGenerator) -> ($T, $T) {
+                        let noise = generator.noise.update();
+                        let envelope = generator.envelope.update();
+                        generator.channels.iter_mut().fold(
+                            (Default::default(), Default::default()),
+                            |(left, right): ($T, $T), channel| {
+                                let value = unsafe {
+                                    *[<OUTPUT_VOLUME_TABLE_$T>].get_unchecked(channel.update(noise, envelope) as usize)
+                                };
+                                let (gain_left, gain_right) = channel.pan_gains();
+                                (
+                                    left + value.scale_pan(gain_left),
+                                    right + value.scale_pan(gain_right),
+                                )
+                            },
+                        )
+                    }
                 }
             }
         )*
-
     }
 }
 
+// `antialias` ticks the tone/noise/envelope generators at an oversampled
+// rate (see `SoundGenerator::new`) and only the `f32` path below decimates
+// that back down; there is no oversampled `i16` path, so drop this impl
+// rather than silently emit audio pitched down by the oversample factor
+// (three octaves at `OVERSAMPLE` = 8).
+#[cfg(not(feature = "antialias"))]
 output_mixer_impl! {
     {i16, 1 << 1}
 }
+#[cfg(not(feature = "antialias"))]
+output_mixer_default_impl!(i16);
 
 #[cfg(feature = "float")]
 output_mixer_impl! {
     {f32, u16::MAX }
 }
 
+#[cfg(all(feature = "float", not(feature = "antialias")))]
+output_mixer_default_impl!(f32);
+
+// With `antialias`, the tone/noise/envelope generators already tick at the
+// oversampled rate (see `SoundGenerator::new`); push each oversampled mix into
+// the decimator and return the filtered sample instead of a raw thinned one.
+#[cfg(all(feature = "float", feature = "antialias"))]
+impl OutputSample<f32> for f32 {
+    fn next_sample(generator: &mut SoundGenerator) -> f32 {
+        for _ in 0..OVERSAMPLE {
+            let noise = generator.noise.update();
+            let envelope = generator.envelope.update();
+            let mix = generator.channels.iter_mut().fold(0.0f32, |sum, channel| {
+                sum + unsafe {
+                    OUTPUT_VOLUME_TABLE_f32.get_unchecked(channel.update(noise, envelope) as usize)
+                }
+            });
+            generator.decimator.push(mix);
+        }
+        generator.decimator.convolve()
+    }
+
+    fn fill(generator: &mut SoundGenerator, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = Self::next_sample(generator);
+        }
+    }
+
+    fn next_sample_stereo(generator: &mut SoundGenerator) -> (f32, f32) {
+        for _ in 0..OVERSAMPLE {
+            let noise = generator.noise.update();
+            let envelope = generator.envelope.update();
+            let (mix_l, mix_r) = generator.channels.iter_mut().fold(
+                (0.0f32, 0.0f32),
+                |(left, right), channel| {
+                    let value = unsafe {
+                        *OUTPUT_VOLUME_TABLE_f32.get_unchecked(channel.update(noise, envelope) as usize)
+                    };
+                    let (gain_left, gain_right) = channel.pan_gains();
+                    (left + value.scale_pan(gain_left), right + value.scale_pan(gain_right))
+                },
+            );
+            generator.decimator.push(mix_l);
+            generator.decimator_r.push(mix_r);
+        }
+        (generator.decimator.convolve(), generator.decimator_r.convolve())
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{Output, SoundGenerator};
+    use crate::{EnvelopeGenerator, EnvelopeShape, Output, ToneGenerator, Waveform};
 
     #[test]
+    fn sawtooth_and_triangle_match_square_cycle_length() {
+        const CLOCK_RATE: u32 = 2_000_000;
+        const SAMPLE_RATE: u32 = CLOCK_RATE / 8;
+        const PERIOD: u16 = 100;
+        const TICKS: usize = 200_000;
+
+        let mut square = ToneGenerator::new(CLOCK_RATE, SAMPLE_RATE);
+        square.set_period(PERIOD);
+        let square_cycles = count_square_cycles(&mut square, TICKS);
+
+        let mut sawtooth = ToneGenerator::new(CLOCK_RATE, SAMPLE_RATE);
+        sawtooth.set_period(PERIOD);
+        sawtooth.set_waveform(Waveform::Sawtooth);
+        let sawtooth_cycles = count_sawtooth_wraps(&mut sawtooth, TICKS);
+
+        let mut triangle = ToneGenerator::new(CLOCK_RATE, SAMPLE_RATE);
+        triangle.set_period(PERIOD);
+        triangle.set_waveform(Waveform::Triangle);
+        let triangle_cycles = count_triangle_cycles(&mut triangle, TICKS);
+
+        // Same tone period should produce the same pitch regardless of
+        // waveform shape, within a tolerance for rounding the ramp into
+        // whole sub-steps.
+        let lower = square_cycles * 9 / 10;
+        let upper = square_cycles * 11 / 10;
+        assert!(
+            (lower..=upper).contains(&sawtooth_cycles),
+            "square={square_cycles} sawtooth={sawtooth_cycles}"
+        );
+        assert!(
+            (lower..=upper).contains(&triangle_cycles),
+            "square={square_cycles} triangle={triangle_cycles}"
+        );
+    }
+
+    fn count_square_cycles(generator: &mut ToneGenerator, ticks: usize) -> usize {
+        let mut cycles = 0;
+        let mut prev_tone = generator.update().0.contains(Output::TONE);
+        for _ in 1..ticks {
+            let tone = generator.update().0.contains(Output::TONE);
+            if tone && !prev_tone {
+                cycles += 1;
+            }
+            prev_tone = tone;
+        }
+        cycles
+    }
+
+    fn count_sawtooth_wraps(generator: &mut ToneGenerator, ticks: usize) -> usize {
+        let mut cycles = 0;
+        let mut prev_ramp = generator.update().1;
+        for _ in 1..ticks {
+            let ramp = generator.update().1;
+            if ramp < prev_ramp {
+                cycles += 1;
+            }
+            prev_ramp = ramp;
+        }
+        cycles
+    }
+
+    // Triangle ramps up then down, so a falling sample no longer marks a full
+    // cycle (unlike sawtooth's instant wrap) — count direction reversals
+    // instead and divide by two (one rising leg + one falling leg per cycle).
+    fn count_triangle_cycles(generator: &mut ToneGenerator, ticks: usize) -> usize {
+        let mut reversals = 0;
+        let mut increasing = true;
+        let mut prev_ramp = generator.update().1;
+        for _ in 1..ticks {
+            let ramp = generator.update().1;
+            if increasing && ramp < prev_ramp {
+                reversals += 1;
+                increasing = false;
+            } else if !increasing && ramp > prev_ramp {
+                reversals += 1;
+                increasing = true;
+            }
+            prev_ramp = ramp;
+        }
+        reversals / 2
+    }
+
+    #[test]
+    fn envelope_holds_at_zero_when_continue_is_clear() {
+        const CLOCK_RATE: u32 = 2_000_000;
+        const SAMPLE_RATE: u32 = CLOCK_RATE / 8;
+
+        // With CONTINUE clear, every shape must decay to and hold at level 0,
+        // regardless of ATTACK (it previously held at 15 when ATTACK was set).
+        for shape in [EnvelopeShape::empty(), EnvelopeShape::ATTACK] {
+            let mut envelope = EnvelopeGenerator::new(CLOCK_RATE, SAMPLE_RATE);
+            envelope.set_period(1);
+            envelope.set_shape(shape);
+
+            let mut level = 0;
+            for _ in 0..10_000 {
+                level = envelope.update();
+            }
+            assert_eq!(level, 0, "shape {:#06b} should hold at 0", shape.bits());
+        }
+    }
+
+    // Exercises the `i16` output path, which `antialias` removes (see the
+    // crate-level `antialias` feature docs).
+    #[test]
+    #[cfg(not(feature = "antialias"))]
     fn test() {
+        use crate::SoundGenerator;
+
         const CLOCK_RATE: u32 = 2_000_0000;
         const SAMPLE_RATE: u32 = CLOCK_RATE / 8;
         let mut generator = SoundGenerator::new(CLOCK_RATE, SAMPLE_RATE);
@@ -415,4 +1177,133 @@ mod tests {
         });
         assert_eq!(zero, non_zero);
     }
+
+    // Regression test for the i16 stereo pan scaling truncation bug fixed
+    // alongside `PanScale`: dividing the volume-table value by 255 before
+    // multiplying by the gain rounded every low volume level to 0, so a
+    // hard-panned low-volume channel went silent on its own (non-muted) side.
+    #[test]
+    #[cfg(not(feature = "antialias"))]
+    fn stereo_pan_scales_low_volume_without_truncating() {
+        use crate::SoundGenerator;
+
+        const CLOCK_RATE: u32 = 2_000_000;
+        const SAMPLE_RATE: u32 = CLOCK_RATE / 8;
+        const TICKS: usize = 64;
+
+        let mut generator = SoundGenerator::new(CLOCK_RATE, SAMPLE_RATE);
+        generator.set_mode(0, Output::TONE);
+        generator.set_period(0, 1);
+        generator.set_volume(0, 3);
+        generator.set_pan(0, 255);
+
+        let mut left_non_zero = false;
+        let mut right_non_zero = false;
+        for _ in 0..TICKS {
+            let (left, right): (i16, i16) = generator.next_sample_stereo();
+            left_non_zero |= left != 0;
+            right_non_zero |= right != 0;
+        }
+        assert!(
+            right_non_zero,
+            "hard-right pan at low volume must still produce non-zero right samples"
+        );
+        assert!(
+            !left_non_zero,
+            "hard-right pan must fully silence the left channel"
+        );
+
+        // A middling pan should scale each side roughly in proportion to its
+        // gain rather than collapsing to 0 on the quieter side.
+        let mut generator = SoundGenerator::new(CLOCK_RATE, SAMPLE_RATE);
+        generator.set_mode(0, Output::TONE);
+        generator.set_period(0, 1);
+        generator.set_volume(0, 3);
+        generator.set_pan(0, 64);
+
+        left_non_zero = false;
+        right_non_zero = false;
+        for _ in 0..TICKS {
+            let (left, right): (i16, i16) = generator.next_sample_stereo();
+            left_non_zero |= left != 0;
+            right_non_zero |= right != 0;
+        }
+        assert!(left_non_zero, "left (majority gain) side should be audible");
+        assert!(right_non_zero, "right (minority gain) side should still be audible, not truncated to 0");
+    }
+
+    #[test]
+    fn write_register_combines_tone_period_fine_and_coarse() {
+        use crate::SoundGenerator;
+
+        let mut generator = SoundGenerator::new(2_000_000, 250_000);
+        // R0/R1 are channel 0's fine/coarse tone period; only the low nibble
+        // of the coarse register is part of the 12-bit period.
+        generator.write_register(0, 0x34);
+        generator.write_register(1, 0xf2);
+        assert_eq!(generator.channels[0].generator.period, 0x234);
+    }
+
+    #[test]
+    fn write_register_mixer_bits_are_active_low() {
+        use crate::SoundGenerator;
+
+        let mut generator = SoundGenerator::new(2_000_000, 250_000);
+        // bit layout: bit(n)=tone disable, bit(n+3)=noise disable, n=channel.
+        // channel 0: tone enabled, noise disabled.
+        // channel 1: tone disabled, noise enabled.
+        // channel 2: tone disabled, noise disabled.
+        generator.write_register(7, 0b0010_1110);
+        assert_eq!(generator.channels[0].mode.bits(), Output::TONE.bits());
+        assert_eq!(generator.channels[1].mode.bits(), Output::NOISE.bits());
+        assert_eq!(generator.channels[2].mode.bits(), Output::NONE.bits());
+    }
+
+    #[test]
+    fn write_register_splits_volume_and_envelope_select_bit() {
+        use crate::SoundGenerator;
+
+        let mut generator = SoundGenerator::new(2_000_000, 250_000);
+        // R8: channel 0 volume/envelope-select. Bit 4 selects the envelope;
+        // the low nibble is the fixed volume when it's clear.
+        generator.write_register(8, 0b0001_1010);
+        assert_eq!(generator.channels[0].volume, 0x0a);
+        assert!(generator.channels[0].use_envelope);
+
+        generator.write_register(8, 0b0000_0111);
+        assert_eq!(generator.channels[0].volume, 0x07);
+        assert!(!generator.channels[0].use_envelope);
+    }
+
+    // Pins generate()/fill() to produce exactly the same samples as calling
+    // next_sample() in a loop.
+    #[test]
+    #[cfg(not(feature = "antialias"))]
+    fn generate_matches_repeated_next_sample() {
+        use crate::SoundGenerator;
+        use core::array;
+
+        const CLOCK_RATE: u32 = 2_000_000;
+        const SAMPLE_RATE: u32 = CLOCK_RATE / 8;
+        const SAMPLES: usize = 256;
+
+        fn make_generator() -> SoundGenerator {
+            let mut generator = SoundGenerator::new(CLOCK_RATE, SAMPLE_RATE);
+            generator.set_mode(0, Output::TONE);
+            generator.set_volume(0, 11);
+            generator.set_period(0, 5);
+            generator.set_mode(1, Output::NOISE);
+            generator.set_volume(1, 6);
+            generator
+        }
+
+        let mut looped = make_generator();
+        let expected: [i16; SAMPLES] = array::from_fn(|_| looped.next_sample());
+
+        let mut batched = make_generator();
+        let mut actual = [0i16; SAMPLES];
+        batched.generate(&mut actual);
+
+        assert_eq!(actual, expected);
+    }
 }